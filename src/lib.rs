@@ -4,7 +4,7 @@ Format XML Templating
 
 Minimal compile time templating for XML in Rust!
 
-The `format_xml!` macro by example accepts an XML-like syntax and transforms it into a `format_args!` invocation.
+The `format_xml!` macro by example accepts an XML-like syntax and transforms it into a value implementing `Display`, built on top of `write_xml!`.
 We say _XML-like_ because due to limitations of the macro system some concessions had to be made, see the examples below.
 
 Examples
@@ -103,7 +103,41 @@ format_xml! {
 
 The resulting string is `<h1>Hello World</h1><ul><li>1*5=5</li><li>2*5=10</li><li>3*5=15</li><li>4*5=20</li><li>5*5=25</li></ul>`.
 
-Control flow are currently only supported outside tags. They are not supported in attributes. The expressions for `if` and `for` must be surrounded with parentheses due to macro by example limitations.
+The expressions for `if` and `for` must be surrounded with parentheses due to macro by example limitations.
+
+### Control flow in attributes
+
+```rust
+# use format_xml::format_xml;
+let secure = true;
+let extra_classes = ["a", "b"];
+
+# let result =
+format_xml! {
+	<a href=(if (secure) { "https://example.com" } else { "http://example.com" })
+		for name in (extra_classes.iter()) { data_tag={name} }
+	>"Link"</a>
+}.to_string()
+# ; assert_eq!(result, r#"<a href="https://example.com" data_tag="a" data_tag="b">Link</a>"#);
+```
+
+`if`/`match` are also allowed inside the parentheses of an attribute *value*: every branch must resolve to something `Display`-able, and the result is written inside the surrounding quotes. `for` and `if` in attribute *name* position (i.e. where a whole `name="value"` pair is expected) emit zero or more complete pairs, with the leading space handled for you.
+
+### Escape hatch
+
+```rust
+# use format_xml::format_xml;
+let width = 8;
+let x = 42;
+
+# let result =
+format_xml! {
+	<span>|f| { write!(f, "{:>width$}", x, width = width)?; }</span>
+}.to_string()
+# ; assert_eq!(result, r#"<span>      42</span>"#);
+```
+
+`|f| { ... }` is an escape hatch for anything the rest of the syntax cannot express: `f` is bound to the underlying sink, so the block can `write!` into it directly. It composes with the rest of the macro like any other node and may appear anywhere a node is expected.
 
 ### Specialised attribute syntax
 
@@ -117,21 +151,93 @@ let make_red = true;
 format_xml! {
 	<div class=["class-a": has_a, "class-b": has_b]><span style=["color: red;": make_red]></span></div>
 }.to_string()
-# ; assert_eq!(result, r#"<div class="class-a "><span style="color: red; "></span></div>"#);
+# ; assert_eq!(result, r#"<div class="class-a"><span style="color: red;"></span></div>"#);
 ```
 
-The resulting string is `<div class="class-a "><span style="color: red; "></span></div>`.
+The resulting string is `<div class="class-a"><span style="color: red;"></span></div>`.
+
+Dedicated syntax for a fixed set of space delimited attribute values where each element can be conditionally included, joined cleanly with no trailing separator. This is specifically designed to work with the style and class attributes of html.
+
+The same bracket syntax also accepts a dynamic source, for a set of classes that isn't known until runtime:
+
+```rust
+# use format_xml::format_xml;
+let classes = ["a", "b"];
+let styles = [("color", "red"), ("font-weight", "bold")];
+
+# let result =
+format_xml! {
+	<div class=[for c in (classes.iter()) { c }]></div>
+	<div class=[..classes.iter()]></div>
+	<div style=[..styles.iter().copied()]></div>
+}.to_string()
+# ; assert_eq!(result, r#"<div class="a b"></div><div class="a b"></div><div style="color: red; font-weight: bold;"></div>"#);
+```
+
+`class=[for PAT in (iter) { item }]` and `class=[..iter]` join whatever `Display`-able items the iterator yields, space separated, with no trailing separator; the latter is shorthand for iterating the items as given. `style=[..iter]` takes an iterator of `("prop", value)` pairs and renders each as `prop: value;`.
+
+### Streaming into a buffer
+
+```rust
+# use format_xml::write_xml;
+use std::fmt::Write;
+
+let mut buf = String::new();
+for i in 1..=3 {
+	write_xml!(buf, {
+		<li>{i}</li>
+	}).unwrap();
+}
+# assert_eq!(buf, "<li>1</li><li>2</li><li>3</li>");
+```
+
+`write_xml!(buf, { ... })` lowers the same syntax into a sequence of `write!(buf, ...)` statements against a `&mut impl fmt::Write`, evaluating `buf` once. `for`/`if` become real control flow pushing straight into `buf`, rather than nesting a nested `FnFmt` closure per branch, which avoids an allocation per outer call when building large or dynamically-assembled documents in a loop. The expression evaluates to a `Result<(), _>`. `io_write_xml!` is the same macro for an `io::Write` sink. `format_xml!` is implemented on top of `write_xml!`.
+
+### Automatic escaping
+
+```rust
+# use format_xml::format_xml_escaped;
+let name = "<World>";
+
+# let result =
+format_xml_escaped! {
+	<p>"Hello, " {name} "!"</p>
+}.to_string()
+# ; assert_eq!(result, r#"<p>Hello, &lt;World&gt;!</p>"#);
+```
+
+`format_xml_escaped!` is a drop-in replacement for `format_xml!` that escapes `&<>"'` as their XML entities in text nodes and `{value}`/`{value;spec}` interpolations, including attribute values. Raw-mode blocks (comments, CDATA, doctype) are left untouched, since their content isn't XML text. Use this whenever any of the formatted values could be untrusted input.
+
+### Compile-time tag balance checking
+
+```rust
+# use format_xml::format_xml_strict;
+# let result =
+format_xml_strict! {
+	<a><b>"text"</b></a>
+}.to_string()
+# ; assert_eq!(result, r#"<a><b>text</b></a>"#);
+```
+
+Well-balanced input is accepted and renders exactly like `format_xml!` would. Mismatched or unclosed tags are rejected at compile time instead:
+
+```rust,compile_fail
+# use format_xml::format_xml_strict;
+format_xml_strict! {
+	<a></b>
+}
+```
 
-Dedicated syntax for fixed set of space delimited attribute values where each element can be conditionally included. This is specifically designed to work with the style and class attributes of html.
+`format_xml_strict!` is a drop-in replacement for `format_xml!` that additionally rejects mismatched and unclosed tags with a `compile_error!`, e.g. the above fails to compile with a message naming both `<a>` and `</b>`. Self-closing tags and the doctype/PI/comment/CDATA forms are exempt, since they have no matching close tag to check. `format_xml!` itself remains as permissive as before.
 
 Limitations
 -----------
 
 This crate is implemented with standard macros by example (`macro_rules!`). Because of this there are various limitations:
 
-* It is not possible to check whether tags are closed by the appropriate closing tag. This crate will happily accept `<open></close>`. It does enforce more simple lexical rules such as rejecting `</tag/>`.
+* `format_xml!` does not check whether tags are closed by the appropriate closing tag. This crate will happily accept `<open></close>`. It does enforce more simple lexical rules such as rejecting `</tag/>`. Use `format_xml_strict!` if you want this checked at compile time.
 
-* Escaping of `&<>"'` is not automatic. You can trivially break the structure by including these characters in either the formatting string or formatted values. Avoid untrusted input!
+* Escaping of `&<>"'` is not automatic with `format_xml!`. You can trivially break the structure by including these characters in either the formatting string or formatted values. Avoid untrusted input, or use `format_xml_escaped!` instead.
 
 * The formatting specifiers are separated from its value by a semicolon instead of a colon.
 
@@ -147,6 +253,7 @@ mod util;
 pub use self::util::*;
 
 mod xml;
+pub use self::xml::{__write_xml_run, __io_write_xml_run};
 
 /// Implements `std::fmt::Display` for the Fn closure matching fmt's signature.
 #[derive(Copy, Clone)]
@@ -0,0 +1,140 @@
+/*!
+Small `Display` adapters used by the expansion of the `format_xml!` macro.
+
+These are implementation details of the macro but are exposed publicly
+(`pub use self::util::*` in the crate root) because the expanded code
+refers to them by their `$crate`-qualified path.
+*/
+
+use std::fmt;
+
+/// Formats the wrapped value, escaping `&`, `<`, `>`, `"` and `'` as their
+/// XML entities as the underlying `Display::fmt` writes through it.
+///
+/// This is what `format_xml_escaped!` wraps interpolated values and
+/// attribute values in. Analogous to [`FnFmt`](crate::FnFmt), it wraps the
+/// formatting step itself rather than pre-rendering into an owned `String`:
+/// runs of unescaped characters are flushed straight through to the
+/// destination formatter, and only the five special characters are
+/// substituted.
+#[derive(Copy, Clone)]
+pub struct Escape<T: fmt::Display>(pub T);
+impl<T: fmt::Display> fmt::Display for Escape<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use fmt::Write;
+		write!(EscapeWriter(f), "{}", self.0)
+	}
+}
+
+struct EscapeWriter<'a, 'b>(&'a mut fmt::Formatter<'b>);
+impl<'a, 'b> fmt::Write for EscapeWriter<'a, 'b> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut last = 0;
+		for (i, c) in s.char_indices() {
+			let entity = match c {
+				'&' => "&amp;",
+				'<' => "&lt;",
+				'>' => "&gt;",
+				'"' => "&quot;",
+				'\'' => "&#39;",
+				_ => continue,
+			};
+			if last < i {
+				self.0.write_str(&s[last..i])?;
+			}
+			self.0.write_str(entity)?;
+			last = i + c.len_utf8();
+		}
+		if last < s.len() {
+			self.0.write_str(&s[last..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Renders a fixed set of literal fragments, each conditionally included,
+/// space separated with no trailing separator.
+///
+/// This backs the `class=["a": cond_a, "b": cond_b]` specialised attribute
+/// syntax.
+#[derive(Copy, Clone)]
+pub struct CondList<'a>(pub &'a [(&'a str, bool)]);
+impl<'a> fmt::Display for CondList<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut wrote = false;
+		for &(text, cond) in self.0 {
+			if cond {
+				if wrote {
+					f.write_str(" ")?;
+				}
+				f.write_str(text)?;
+				wrote = true;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Renders the items produced by a runtime iterator, space separated with
+/// no trailing separator.
+///
+/// This backs the dynamic `class=[for c in (classes) { c }]` and
+/// `class=[..classes]` specialised attribute forms. The iterator itself is
+/// stored (rather than the iterable it was built from) and cloned on each
+/// `fmt` call, since `fmt` only gets `&self` but `Display::fmt` may in
+/// principle run more than once; an iterator produced from a factory
+/// closure instead would tie its item lifetimes to that closure's own
+/// captures rather than to `&self`, which doesn't type-check once the
+/// iterator borrows from data the closure owns.
+#[derive(Copy, Clone)]
+pub struct IterJoin<I>(pub I);
+impl<I: Clone + Iterator> fmt::Display for IterJoin<I>
+where
+	I::Item: fmt::Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut wrote = false;
+		for item in self.0.clone() {
+			if wrote {
+				f.write_str(" ")?;
+			}
+			write!(f, "{}", item)?;
+			wrote = true;
+		}
+		Ok(())
+	}
+}
+
+/// Byte-wise equality of two `&str`s, callable from a `const` context.
+///
+/// Backs `format_xml_strict!`'s compile-time tag balance check: an opening
+/// and closing tag's (possibly hyphen-/colon-separated) names are each
+/// joined into a single string via `concat!`, and compared here inside a
+/// `const` item so a mismatch surfaces as a compile error.
+#[doc(hidden)]
+pub const fn __format_xml_str_eq(a: &str, b: &str) -> bool {
+	let (a, b) = (a.as_bytes(), b.as_bytes());
+	if a.len() != b.len() {
+		return false;
+	}
+	let mut i = 0;
+	while i < a.len() {
+		if a[i] != b[i] {
+			return false;
+		}
+		i += 1;
+	}
+	true
+}
+
+/// Renders a `(name, value)` pair as `name: value;`.
+///
+/// This backs the `("prop", value)` pairs of the dynamic `style=[..pairs]`
+/// attribute form; a sequence of these is what [`IterJoin`] space-joins.
+#[derive(Copy, Clone)]
+pub struct StyleProp<P: fmt::Display, V: fmt::Display>(pub P, pub V);
+impl<P: fmt::Display, V: fmt::Display> fmt::Display for StyleProp<P, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: {};", self.0, self.1)
+	}
+}
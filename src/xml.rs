@@ -0,0 +1,1443 @@
+/*!
+The `macro_rules!` tt-munchers that implement `format_xml!` and friends.
+
+[`write_xml!`] is the primitive: it walks the input token tree left to
+right and lowers each node directly into a `write!` call against a
+`fmt::Write`/`io::Write` sink, using real `if`/`for`/`match` statements for
+control flow instead of nesting closures. [`format_xml!`] is implemented
+on top of it, wrapping the statement sequence in a [`FnFmt`](crate::FnFmt)
+closure that writes into a `fmt::Formatter` (which itself implements
+`fmt::Write`).
+*/
+
+/// Streams an XML-like template into a `fmt::Write` sink as a sequence of
+/// `write!` calls, instead of building one `format_args!` value.
+///
+/// Useful for large or dynamically-assembled documents: `for`/`if` become
+/// real control flow pushing into `buf` as they go, rather than each
+/// nesting another `FnFmt` closure and an intermediate allocation. `buf`
+/// is evaluated once. See the crate documentation for the accepted syntax.
+#[macro_export]
+macro_rules! write_xml {
+	($buf:expr, { $($body:tt)* }) => {
+		$crate::__write_xml_run(&mut $buf, move |buf| {
+			$crate::__write_xml_body!(@parse buf; $($body)*)
+		})
+	};
+}
+
+/// Runs `f` against `buf`, letting the `B: fmt::Write` bound drive
+/// inference for `f`'s parameter instead of annotating it.
+///
+/// A closure's signature is checked against its own body before the call
+/// site unifies it with the argument, so a `(move |buf: &mut _| { ... })(&mut buf)`
+/// IIFE leaves `_` unconstrained. A generic function parameter, by
+/// contrast, is specialised per call site, so routing the call through
+/// here lets it infer correctly.
+#[doc(hidden)]
+pub fn __write_xml_run<B: ::std::fmt::Write + ?Sized>(buf: &mut B, f: impl FnOnce(&mut B) -> ::std::fmt::Result) -> ::std::fmt::Result {
+	f(buf)
+}
+
+/// Like [`write_xml!`], but for an `io::Write` sink.
+#[macro_export]
+macro_rules! io_write_xml {
+	($buf:expr, { $($body:tt)* }) => {
+		$crate::__io_write_xml_run(&mut $buf, move |buf| {
+			$crate::__write_xml_body!(@parse buf; $($body)*)
+		})
+	};
+}
+
+/// Like [`__write_xml_run`], but for an `io::Write` sink; kept separate
+/// since `fmt::Write` and `io::Write` have distinct `Error` types that a
+/// single generic function cannot unify.
+#[doc(hidden)]
+pub fn __io_write_xml_run<B: ::std::io::Write + ?Sized>(buf: &mut B, f: impl FnOnce(&mut B) -> ::std::io::Result<()>) -> ::std::io::Result<()> {
+	f(buf)
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_body {
+	// Done, nothing left to munch.
+	(@parse $buf:expr;) => {
+		Ok(())
+	};
+
+	// Closing tag. The name may be hyphen-/colon-separated (e.g. `tag-name`,
+	// `ns:tag`), which lexes as several idents punctuated by `-`/`:`, not a
+	// single `ident` token; `__write_xml_tagname!` munches the rest of it.
+	(@parse $buf:expr; </ $name:ident $($rest:tt)*) => {
+		{
+			write!($buf, "</{}", stringify!($name))?;
+			$crate::__write_xml_tagname!(@close $buf; $($rest)*)
+		}
+	};
+
+	// Opening tag, hands off to the attribute muncher once the (possibly
+	// hyphen-/colon-separated) name is fully written.
+	(@parse $buf:expr; < $name:ident $($rest:tt)*) => {
+		{
+			write!($buf, "<{}", stringify!($name))?;
+			$crate::__write_xml_tagname!(@open $buf; $($rest)*)
+		}
+	};
+
+	// `<!doctype ...>`
+	(@parse $buf:expr; < ! doctype $($rest:tt)*) => {
+		{
+			write!($buf, "<!doctype")?;
+			$crate::__write_xml_raw!(@doctype $buf; $($rest)*)
+		}
+	};
+
+	// `<?target ...?>`
+	(@parse $buf:expr; < ? $target:ident $($rest:tt)*) => {
+		{
+			write!($buf, "<?{}", stringify!($target))?;
+			$crate::__write_xml_tagname!(@pi $buf; $($rest)*)
+		}
+	};
+
+	// `<!-- "comment" -->`
+	(@parse $buf:expr; < ! - - $($rest:tt)*) => {
+		{
+			write!($buf, "<!-- ")?;
+			$crate::__write_xml_raw!(@comment $buf; $($rest)*)
+		}
+	};
+
+	// `<![CDATA["cdata"]]>`
+	(@parse $buf:expr; < ! [ CDATA [ $($content:tt)* ] ] > $($rest:tt)*) => {
+		{
+			write!($buf, "<![CDATA[")?;
+			$crate::__write_xml_raw!(@cdata $buf; $($content)*)?;
+			write!($buf, "]]>")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// `if let PAT = (expr) { body } else if (expr) { body } else { body }`
+	(@parse $buf:expr; if let $pat:pat = ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_if!(@tail $buf; [if let $pat = ($cond) { $crate::__write_xml_body!(@parse $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	// `if (expr) { body } else if (expr) { body } else { body }`
+	(@parse $buf:expr; if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_if!(@tail $buf; [if $cond { $crate::__write_xml_body!(@parse $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	// `for PAT in (expr) { body }`
+	(@parse $buf:expr; for $pat:pat in ( $iter:expr ) { $($body:tt)* } $($rest:tt)*) => {
+		{
+			for $pat in $iter {
+				$crate::__write_xml_body!(@parse $buf; $($body)*)?;
+			}
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// `match (expr) { pat => { body } ... }`
+	(@parse $buf:expr; match ( $e:expr ) { $($arms:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_match!(@arms $buf; [$e] [] $($arms)* @@ $($rest)*)
+	};
+
+	// A plain (unbound) statement, e.g. a `let` binding between nodes.
+	(@parse $buf:expr; let $pat:pat = $e:expr ; $($rest:tt)*) => {
+		{
+			let $pat = $e;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// `{value;spec}` interpolation with a formatting specifier.
+	(@parse $buf:expr; { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		{
+			write!($buf, concat!("{:", $(stringify!($spec)),* , "}"), $e)?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// `{value}` interpolation.
+	(@parse $buf:expr; { $e:expr } $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $e)?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// `|f| { ... }` escape hatch: hands the user a mutable reborrow of the
+	// sink directly, for cases the rest of the syntax cannot express. See
+	// the crate documentation.
+	(@parse $buf:expr; |$f:ident| { $($block:tt)* } $($rest:tt)*) => {
+		{
+			let $f = &mut *$buf;
+			$($block)*
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// Text literal.
+	(@parse $buf:expr; $text:literal $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $text)?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+}
+
+/// Munches the hyphen-/colon-separated remainder of a tag or PI target name
+/// (e.g. the `-name` of `tag-name`, the `:tag` of `ns:tag`), writing each
+/// segment as it goes, then resumes at the right place for `@ctx`: `@open`
+/// and `@pi` hand off into [`__write_xml_attrs!`] (as `@parse`/`@pi`
+/// respectively), `@close` expects the tag's closing `>` directly.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_tagname {
+	(@$ctx:ident $buf:expr; - $next:ident $($rest:tt)*) => {
+		{
+			write!($buf, "-{}", stringify!($next))?;
+			$crate::__write_xml_tagname!(@$ctx $buf; $($rest)*)
+		}
+	};
+	(@$ctx:ident $buf:expr; : $next:ident $($rest:tt)*) => {
+		{
+			write!($buf, ":{}", stringify!($next))?;
+			$crate::__write_xml_tagname!(@$ctx $buf; $($rest)*)
+		}
+	};
+	(@close $buf:expr; > $($rest:tt)*) => {
+		{
+			write!($buf, ">")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+	(@open $buf:expr; $($rest:tt)*) => {
+		$crate::__write_xml_attrs!(@parse $buf; $($rest)*)
+	};
+	(@pi $buf:expr; $($rest:tt)*) => {
+		$crate::__write_xml_attrs!(@pi $buf; $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_if {
+	// `else if (expr) { body }`
+	(@tail $buf:expr; [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_if!(@tail $buf; [$($built)* else if $cond { $crate::__write_xml_body!(@parse $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	// `else { body }`, closes the chain.
+	(@tail $buf:expr; [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		{
+			$($built)* else { $crate::__write_xml_body!(@parse $buf; $($els)*)?; }
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// No `else`: the branch simply writes nothing when untaken.
+	(@tail $buf:expr; [$($built:tt)*] $($rest:tt)*) => {
+		{
+			$($built)*
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_match {
+	// Collect one `pat => { body }` arm at a time.
+	(@arms $buf:expr; [$e:expr] [$($arms:tt)*] $pat:pat => { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_match!(@arms $buf; [$e] [$($arms)* $pat => { $crate::__write_xml_body!(@parse $buf; $($body)*)?; }] $($rest)*)
+	};
+
+	// End of the arm list, marked by the `@@` sentinel.
+	(@arms $buf:expr; [$e:expr] [$($arms:tt)*] @@ $($rest:tt)*) => {
+		{
+			match $e { $($arms)* }
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_attrs {
+	// Self-closing tag.
+	(@parse $buf:expr; / > $($rest:tt)*) => {
+		{
+			write!($buf, " />")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+	// Closing `>` of an opening tag.
+	(@parse $buf:expr; > $($rest:tt)*) => {
+		{
+			write!($buf, ">")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+	// Closing `?>` of a processing instruction.
+	(@pi $buf:expr; ? > $($rest:tt)*) => {
+		{
+			write!($buf, "?>")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+
+	// Attribute list is exhausted (only reachable from `@list`, used by the
+	// `for`/`if` attribute-position forms below).
+	(@list $buf:expr;) => {
+		Ok(())
+	};
+
+	// `for PAT in (expr) { attrs }` in attribute-name position: emits zero
+	// or more complete `name="value"` pairs.
+	(@$ctx:ident $buf:expr; for $pat:pat in ( $iter:expr ) { $($attrs:tt)* } $($rest:tt)*) => {
+		{
+			for $pat in $iter {
+				$crate::__write_xml_attrs!(@list $buf; $($attrs)*)?;
+			}
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `if (expr) { attrs } else if (expr) { attrs } else { attrs }` in
+	// attribute-name position: emits zero or more complete `name="value"`
+	// pairs.
+	(@$ctx:ident $buf:expr; if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrs_if!(@tail $ctx $buf; [if $cond { $crate::__write_xml_attrs!(@list $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	// `style=[..pairs]`: a dynamic list of `("prop", value)` pairs, each
+	// rendered as `prop: value;`.
+	(@$ctx:ident $buf:expr; style = [ .. $iter:expr ] $($rest:tt)*) => {
+		{
+			write!($buf, " style=\"{}\"", $crate::IterJoin(($iter).into_iter().map(|(p, v)| $crate::StyleProp(p, v))))?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// Attribute name: start accumulating (possibly hyphen-/colon-separated,
+	// e.g. `stroke-width`, `xml:lang`) into `[$($joined)*]`, a sequence of
+	// `concat!` pieces, then dispatch on the value form once it's complete.
+	(@$ctx:ident $buf:expr; $name:ident $($rest:tt)*) => {
+		$crate::__write_xml_attr_name!(@$ctx $buf; [stringify!($name),] $($rest)*)
+	};
+}
+
+/// Finishes munching a (possibly hyphen-/colon-separated) attribute name
+/// into `[$($joined)*]`, a sequence of `concat!` pieces, then dispatches on
+/// the value form that follows `=`, resuming [`__write_xml_attrs!`] for the
+/// rest of the attribute list afterwards.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_attr_name {
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] - $next:ident $($rest:tt)*) => {
+		$crate::__write_xml_attr_name!(@$ctx $buf; [$($joined)* "-", stringify!($next),] $($rest)*)
+	};
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] : $next:ident $($rest:tt)*) => {
+		$crate::__write_xml_attr_name!(@$ctx $buf; [$($joined)* ":", stringify!($next),] $($rest)*)
+	};
+
+	// `name=(if (expr) { value } else { value })` and
+	// `name=(match (expr) { pat => { value } ... })` control flow in
+	// attribute *value* position: each branch must resolve to something
+	// `Display`-able, emitted inside the surrounding quotes.
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = ( $($cf:tt)* ) $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\""))?;
+			$crate::__write_xml_attrval!(@parse $buf; $($cf)*)?;
+			write!($buf, "\"")?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `class=["a": cond_a, "b": cond_b]` and `style=[...]` specialised syntax.
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = [ $($text:literal : $cond:expr),* $(,)? ] $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\"{}\""), $crate::CondList(&[$(($text, $cond)),*]))?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `name=[for PAT in (expr) { item }]`: a dynamic, space-separated list
+	// built from a runtime iterator.
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = [ for $pat:pat in ( $iter:expr ) { $item:expr } ] $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\"{}\""), $crate::IterJoin(($iter).into_iter().map(move |$pat| $item)))?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `name=[..items]`: a dynamic, space-separated list built from a
+	// runtime iterator.
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = [ .. $iter:expr ] $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\"{}\""), $crate::IterJoin(($iter).into_iter()))?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `name={value;spec}`
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\"{:", $(stringify!($spec)),* , "}\""), $e)?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `name={value}`
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = { $e:expr } $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\"{}\""), $e)?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// `name="literal"`
+	(@$ctx:ident $buf:expr; [$($joined:tt)*] = $value:literal $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", $($joined)* "=\"{}\""), $value)?;
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_attrs_if {
+	// `else if (expr) { attrs }`
+	(@tail $ctx:ident $buf:expr; [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrs_if!(@tail $ctx $buf; [$($built)* else if $cond { $crate::__write_xml_attrs!(@list $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	// `else { attrs }`, closes the chain.
+	(@tail $ctx:ident $buf:expr; [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		{
+			$($built)* else { $crate::__write_xml_attrs!(@list $buf; $($els)*)?; }
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+
+	// No `else`: the branch simply contributes no attributes when untaken.
+	(@tail $ctx:ident $buf:expr; [$($built:tt)*] $($rest:tt)*) => {
+		{
+			$($built)*
+			$crate::__write_xml_attrs!(@$ctx $buf; $($rest)*)
+		}
+	};
+}
+
+/// Parses the control-flow forms allowed in attribute *value* position
+/// (inside the `( ... )` that follows `name=`). Each branch's body is
+/// parsed like a regular node sequence, so it may mix text, `{value}`
+/// interpolation, and nested control flow; the result is written straight
+/// into the surrounding quotes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_attrval {
+	(@parse $buf:expr;) => {
+		Ok(())
+	};
+
+	(@parse $buf:expr; if let $pat:pat = ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrval_if!(@tail $buf; [if let $pat = ($cond) { $crate::__write_xml_body!(@parse $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	(@parse $buf:expr; if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrval_if!(@tail $buf; [if $cond { $crate::__write_xml_body!(@parse $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	(@parse $buf:expr; match ( $e:expr ) { $($arms:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrval_match!(@arms $buf; [$e] [] $($arms)* @@ $($rest)*)
+	};
+
+	(@parse $buf:expr; { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		{
+			write!($buf, concat!("{:", $(stringify!($spec)),* , "}"), $e)?;
+			$crate::__write_xml_attrval!(@parse $buf; $($rest)*)
+		}
+	};
+
+	(@parse $buf:expr; { $e:expr } $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $e)?;
+			$crate::__write_xml_attrval!(@parse $buf; $($rest)*)
+		}
+	};
+
+	(@parse $buf:expr; $text:literal $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $text)?;
+			$crate::__write_xml_attrval!(@parse $buf; $($rest)*)
+		}
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_attrval_if {
+	(@tail $buf:expr; [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrval_if!(@tail $buf; [$($built)* else if $cond { $crate::__write_xml_body!(@parse $buf; $($then)*)?; }] $($rest)*)
+	};
+
+	(@tail $buf:expr; [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		{
+			$($built)* else { $crate::__write_xml_body!(@parse $buf; $($els)*)?; }
+			$crate::__write_xml_attrval!(@parse $buf; $($rest)*)
+		}
+	};
+
+	(@tail $buf:expr; [$($built:tt)*] $($rest:tt)*) => {
+		{
+			$($built)*
+			$crate::__write_xml_attrval!(@parse $buf; $($rest)*)
+		}
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_attrval_match {
+	(@arms $buf:expr; [$e:expr] [$($arms:tt)*] $pat:pat => { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__write_xml_attrval_match!(@arms $buf; [$e] [$($arms)* $pat => { $crate::__write_xml_body!(@parse $buf; $($body)*)?; }] $($rest)*)
+	};
+
+	(@arms $buf:expr; [$e:expr] [$($arms:tt)*] @@ $($rest:tt)*) => {
+		{
+			match $e { $($arms)* }
+			$crate::__write_xml_attrval!(@parse $buf; $($rest)*)
+		}
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __write_xml_raw {
+	(@doctype $buf:expr; > $($rest:tt)*) => {
+		{
+			write!($buf, ">")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+	(@doctype $buf:expr; $t:tt $($rest:tt)*) => {
+		{
+			write!($buf, concat!(" ", stringify!($t)))?;
+			$crate::__write_xml_raw!(@doctype $buf; $($rest)*)
+		}
+	};
+
+	(@comment $buf:expr; - -> $($rest:tt)*) => {
+		{
+			write!($buf, " -->")?;
+			$crate::__write_xml_body!(@parse $buf; $($rest)*)
+		}
+	};
+	(@comment $buf:expr; $text:literal $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $text)?;
+			$crate::__write_xml_raw!(@comment $buf; $($rest)*)
+		}
+	};
+	(@comment $buf:expr; { $e:expr } $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $e)?;
+			$crate::__write_xml_raw!(@comment $buf; $($rest)*)
+		}
+	};
+
+	(@cdata $buf:expr;) => {
+		Ok(())
+	};
+	(@cdata $buf:expr; $text:literal $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $text)?;
+			$crate::__write_xml_raw!(@cdata $buf; $($rest)*)
+		}
+	};
+	(@cdata $buf:expr; { $e:expr } $($rest:tt)*) => {
+		{
+			write!($buf, "{}", $e)?;
+			$crate::__write_xml_raw!(@cdata $buf; $($rest)*)
+		}
+	};
+}
+
+/// Formats an XML-like template into a value implementing `Display`.
+///
+/// Implemented on top of [`write_xml!`]: the statement sequence is wrapped
+/// in a [`FnFmt`](crate::FnFmt) closure that writes into the `fmt::Formatter`
+/// it's given (which itself implements `fmt::Write`). See the crate
+/// documentation for the accepted syntax.
+#[macro_export]
+macro_rules! format_xml {
+	($($body:tt)*) => {
+		$crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			$crate::write_xml!(*f, { $($body)* })
+		})
+	};
+}
+
+/// Like [`format_xml!`], but text nodes and `{value}`/`{value;spec}`
+/// interpolations (including attribute values) are escaped with
+/// [`Escape`](crate::Escape) so that `&<>"'` can never break the document.
+///
+/// Text literals go through the same runtime [`Escape`](crate::Escape)
+/// adapter as interpolated values: `macro_rules!` has no way to rewrite the
+/// contents of a string literal at expansion time, only to pass it along.
+/// Raw-mode blocks (comments, CDATA, doctype) are left untouched, since
+/// their content is not XML text.
+#[macro_export]
+macro_rules! format_xml_escaped {
+	($($body:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [] [] $($body)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_ebody {
+	// Done, nothing left to munch.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*]) => {
+		format_args!(concat!($($fmt),*) $(, $arg)*)
+	};
+
+	// Closing tag. The name may be hyphen-/colon-separated (e.g. `tag-name`,
+	// `ns:tag`), which lexes as several idents punctuated by `-`/`:`, not a
+	// single `ident` token; `__format_xml_etagname!` munches the rest of it.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] </ $name:ident $($rest:tt)*) => {
+		$crate::__format_xml_etagname!(@close [$($fmt,)* concat!("</", stringify!($name))] [$($arg),*] $($rest)*)
+	};
+
+	// Opening tag, hands off to the escaping attribute muncher once the
+	// (possibly hyphen-/colon-separated) name is fully accumulated.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] < $name:ident $($rest:tt)*) => {
+		$crate::__format_xml_etagname!(@open [$($fmt,)* concat!("<", stringify!($name))] [$($arg),*] $($rest)*)
+	};
+
+	// `<!doctype ...>`, raw: bypasses escaping.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] < ! doctype $($rest:tt)*) => {
+		$crate::__format_xml_eraw!(@doctype [$($fmt,)* "<!doctype"] [$($arg),*] $($rest)*)
+	};
+
+	// `<?target ...?>`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] < ? $target:ident $($rest:tt)*) => {
+		$crate::__format_xml_etagname!(@pi [$($fmt,)* concat!("<?", stringify!($target))] [$($arg),*] $($rest)*)
+	};
+
+	// `<!-- "comment" -->`, raw: bypasses escaping.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] < ! - - $($rest:tt)*) => {
+		$crate::__format_xml_eraw!(@comment [$($fmt,)* "<!-- "] [$($arg),*] $($rest)*)
+	};
+
+	// `<![CDATA["cdata"]]>`, raw: bypasses escaping.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] < ! [ CDATA [ $($content:tt)* ] ] > $($rest:tt)*) => {
+		$crate::__format_xml_eraw!(@cdata [$($fmt,)* "<![CDATA["] [$($arg),*] [$($rest)*] $($content)*)
+	};
+
+	// `if let PAT = (expr) { body } else if (expr) { body } else { body }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] if let $pat:pat = ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eif!(@tail [$($fmt,)* "{}"] [$($arg),*] [if let $pat = ($cond) { $crate::format_xml_escaped!($($then)*) }] $($rest)*)
+	};
+
+	// `if (expr) { body } else if (expr) { body } else { body }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eif!(@tail [$($fmt,)* "{}"] [$($arg),*] [if $cond { $crate::format_xml_escaped!($($then)*) }] $($rest)*)
+	};
+
+	// `for PAT in (expr) { body }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] for $pat:pat in ( $iter:expr ) { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			for $pat in $iter {
+				::std::fmt::Write::write_fmt(f, $crate::format_xml_escaped!($($body)*))?;
+			}
+			Ok(())
+		})] $($rest)*)
+	};
+
+	// `match (expr) { pat => { body } ... }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] match ( $e:expr ) { $($arms:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_ematch!(@arms [$($fmt,)* "{}"] [$($arg),*] [$e] [] $($arms)* @@ $($rest)*)
+	};
+
+	// A plain (unbound) statement, e.g. a `let` binding between nodes.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] let $pat:pat = $e:expr ; $($rest:tt)*) => {
+		{
+			let $pat = $e;
+			$crate::__format_xml_ebody!(@parse [$($fmt),*] [$($arg),*] $($rest)*)
+		}
+	};
+
+	// `{value;spec}` interpolation: the spec is applied to the inner value
+	// first, and the rendered result is what gets escaped.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::Escape(format_args!(concat!("{:", $(stringify!($spec)),* , "}"), $e))] $($rest)*)
+	};
+
+	// `{value}` interpolation.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::Escape($e)] $($rest)*)
+	};
+
+	// `|f| { ... }` escape hatch: the user writes straight into the
+	// formatter, so it is their responsibility to escape if needed.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] |$f:ident| { $($block:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |$f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result { $($block)* Ok(()) })] $($rest)*)
+	};
+
+	// Text literal, escaped at runtime like any other value.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] $text:literal $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::Escape($text)] $($rest)*)
+	};
+}
+
+/// Munches the hyphen-/colon-separated remainder of a tag or PI target name
+/// (e.g. the `-name` of `tag-name`, the `:tag` of `ns:tag`), appending each
+/// segment to `$fmt`, then resumes at the right place for `@ctx`: `@open`
+/// and `@pi` hand off into [`__format_xml_eattrs!`] (as `@parse`/`@pi`
+/// respectively), `@close` expects the tag's closing `>` directly.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_etagname {
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] - $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_etagname!(@$ctx [$($fmt,)* concat!("-", stringify!($next))] [$($arg),*] $($rest)*)
+	};
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] : $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_etagname!(@$ctx [$($fmt,)* concat!(":", stringify!($next))] [$($arg),*] $($rest)*)
+	};
+	(@close [$($fmt:expr),*] [$($arg:expr),*] > $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* ">"] [$($arg),*] $($rest)*)
+	};
+	(@open [$($fmt:expr),*] [$($arg:expr),*] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@parse [$($fmt),*] [$($arg),*] $($rest)*)
+	};
+	(@pi [$($fmt:expr),*] [$($arg:expr),*] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@pi [$($fmt),*] [$($arg),*] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eif {
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eif!(@tail [$($fmt),*] [$($arg),*] [$($built)* else if $cond { $crate::format_xml_escaped!($($then)*) }] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt),*] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { $crate::format_xml_escaped!($($els)*) })
+		})] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt),*] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { format_args!("") })
+		})] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_ematch {
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$e:expr] [$($arms:tt)*] $pat:pat => { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_ematch!(@arms [$($fmt),*] [$($arg),*] [$e] [$($arms)* $pat => { $crate::format_xml_escaped!($($body)*) }] $($rest)*)
+	};
+
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$e:expr] [$($arms:tt)*] @@ $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt),*] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, match $e { $($arms)* })
+		})] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eattrs {
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] / > $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* " />"] [$($arg),*] $($rest)*)
+	};
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] > $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* ">"] [$($arg),*] $($rest)*)
+	};
+	(@pi [$($fmt:expr),*] [$($arg:expr),*] ? > $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "?>"] [$($arg),*] $($rest)*)
+	};
+
+	// Attribute list is exhausted (only reachable from `@list`, used by the
+	// `for`/`if` attribute-position forms below).
+	(@list [$($fmt:expr),*] [$($arg:expr),*]) => {
+		format_args!(concat!($($fmt),*) $(, $arg)*)
+	};
+
+	// `for PAT in (expr) { attrs }` in attribute-name position: emits zero
+	// or more complete `name="value"` pairs.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] for $pat:pat in ( $iter:expr ) { $($attrs:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			for $pat in $iter {
+				::std::fmt::Write::write_fmt(f, $crate::__format_xml_eattrs!(@list [] [] $($attrs)*))?;
+			}
+			Ok(())
+		})] $($rest)*)
+	};
+
+	// `if (expr) { attrs } else if (expr) { attrs } else { attrs }` in
+	// attribute-name position: emits zero or more complete `name="value"`
+	// pairs.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrs_if!(@tail $ctx [$($fmt),*] [$($arg),*] [if $cond { $crate::__format_xml_eattrs!(@list [] [] $($then)*) }] $($rest)*)
+	};
+
+	// `style=[..pairs]`: a dynamic list of `("prop", value)` pairs, each
+	// rendered as `prop: value;`.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] style = [ .. $iter:expr ] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* " style=\"{}\""] [$($arg,)* $crate::IterJoin(($iter).into_iter().map(|(p, v)| $crate::StyleProp(p, v)))] $($rest)*)
+	};
+
+	// Attribute name: start accumulating (possibly hyphen-/colon-separated,
+	// e.g. `stroke-width`, `xml:lang`) into `[$($joined)*]`, a sequence of
+	// `concat!` pieces, then dispatch on the value form once it's complete.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] $name:ident $($rest:tt)*) => {
+		$crate::__format_xml_eattr_name!(@$ctx [$($fmt),*] [$($arg),*] [stringify!($name),] $($rest)*)
+	};
+}
+
+/// Finishes munching a (possibly hyphen-/colon-separated) attribute name
+/// into `[$($joined)*]`, a sequence of `concat!` pieces, then dispatches on
+/// the value form that follows `=`, resuming [`__format_xml_eattrs!`] for
+/// the rest of the attribute list afterwards.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eattr_name {
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] - $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_eattr_name!(@$ctx [$($fmt),*] [$($arg),*] [$($joined)* "-", stringify!($next),] $($rest)*)
+	};
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] : $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_eattr_name!(@$ctx [$($fmt),*] [$($arg),*] [$($joined)* ":", stringify!($next),] $($rest)*)
+	};
+
+	// `name=(if (expr) { value } else { value })` and
+	// `name=(match (expr) { pat => { value } ... })` control flow in
+	// attribute *value* position: each branch must resolve to something
+	// `Display`-able, escaped and emitted inside the surrounding quotes.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = ( $($cf:tt)* ) $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $crate::__format_xml_eattrval!(@parse [] [] $($cf)*))
+		})] $($rest)*)
+	};
+
+	// `class=["a": cond_a, "b": cond_b]` and `style=[...]`: left unescaped,
+	// the fragments are developer-supplied literals, not untrusted input.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = [ $($text:literal : $cond:expr),* $(,)? ] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::CondList(&[$(($text, $cond)),*])] $($rest)*)
+	};
+
+	// `name=[for PAT in (expr) { item }]`: a dynamic, space-separated list
+	// built from a runtime iterator, left unescaped like the rest of this
+	// specialised syntax.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = [ for $pat:pat in ( $iter:expr ) { $item:expr } ] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::IterJoin(($iter).into_iter().map(move |$pat| $item))] $($rest)*)
+	};
+
+	// `name=[..items]`: a dynamic, space-separated list built from a
+	// runtime iterator.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = [ .. $iter:expr ] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::IterJoin(($iter).into_iter())] $($rest)*)
+	};
+
+	// `name={value;spec}`
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::Escape(format_args!(concat!("{:", $(stringify!($spec)),* , "}"), $e))] $($rest)*)
+	};
+
+	// `name={value}`
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::Escape($e)] $($rest)*)
+	};
+
+	// `name="literal"`, escaped at runtime like any other value.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($joined:tt)*] = $value:literal $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::Escape($value)] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eattrs_if {
+	(@tail $ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrs_if!(@tail $ctx [$($fmt),*] [$($arg),*] [$($built)* else if $cond { $crate::__format_xml_eattrs!(@list [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@tail $ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { $crate::__format_xml_eattrs!(@list [] [] $($els)*) })
+		})] $($rest)*)
+	};
+
+	(@tail $ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_eattrs!(@$ctx [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { format_args!("") })
+		})] $($rest)*)
+	};
+}
+
+/// Parses the control-flow forms allowed in attribute *value* position
+/// (inside the `( ... )` that follows `name=`), escaping text and
+/// interpolations the same way [`__format_xml_ebody!`] does.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eattrval {
+	(@parse [$($fmt:expr),*] [$($arg:expr),*]) => {
+		format_args!(concat!($($fmt),*) $(, $arg)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] if let $pat:pat = ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval_if!(@tail [$($fmt),*] [$($arg),*] [if let $pat = ($cond) { $crate::__format_xml_eattrval!(@parse [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval_if!(@tail [$($fmt),*] [$($arg),*] [if $cond { $crate::__format_xml_eattrval!(@parse [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] match ( $e:expr ) { $($arms:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval_match!(@arms [$($fmt),*] [$($arg),*] [$e] [] $($arms)* @@ $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::Escape(format_args!(concat!("{:", $(stringify!($spec)),* , "}"), $e))] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::Escape($e)] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] $text:literal $($rest:tt)*) => {
+		$crate::__format_xml_eattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::Escape($text)] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eattrval_if {
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval_if!(@tail [$($fmt),*] [$($arg),*] [$($built)* else if $cond { $crate::__format_xml_eattrval!(@parse [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { $crate::__format_xml_eattrval!(@parse [] [] $($els)*) })
+		})] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_eattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { format_args!("") })
+		})] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eattrval_match {
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$e:expr] [$($arms:tt)*] $pat:pat => { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_eattrval_match!(@arms [$($fmt),*] [$($arg),*] [$e] [$($arms)* $pat => { $crate::__format_xml_eattrval!(@parse [] [] $($body)*) }] $($rest)*)
+	};
+
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$e:expr] [$($arms:tt)*] @@ $($rest:tt)*) => {
+		$crate::__format_xml_eattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, match $e { $($arms)* })
+		})] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_eraw {
+	(@doctype [$($fmt:expr),*] [$($arg:expr),*] > $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* ">"] [$($arg),*] $($rest)*)
+	};
+	(@doctype [$($fmt:expr),*] [$($arg:expr),*] $t:tt $($rest:tt)*) => {
+		$crate::__format_xml_eraw!(@doctype [$($fmt,)* concat!(" ", stringify!($t))] [$($arg),*] $($rest)*)
+	};
+
+	(@comment [$($fmt:expr),*] [$($arg:expr),*] - -> $($rest:tt)*) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* " -->"] [$($arg),*] $($rest)*)
+	};
+	(@comment [$($fmt:expr),*] [$($arg:expr),*] $text:literal $($rest:tt)*) => {
+		$crate::__format_xml_eraw!(@comment [$($fmt,)* "{}"] [$($arg,)* $text] $($rest)*)
+	};
+	(@comment [$($fmt:expr),*] [$($arg:expr),*] { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_eraw!(@comment [$($fmt,)* "{}"] [$($arg,)* $e] $($rest)*)
+	};
+
+	(@cdata [$($fmt:expr),*] [$($arg:expr),*] [$($rest:tt)*]) => {
+		$crate::__format_xml_ebody!(@parse [$($fmt,)* "]]>"] [$($arg),*] $($rest)*)
+	};
+	(@cdata [$($fmt:expr),*] [$($arg:expr),*] [$($rest:tt)*] $text:literal $($content:tt)*) => {
+		$crate::__format_xml_eraw!(@cdata [$($fmt,)* "{}"] [$($arg,)* $text] [$($rest)*] $($content)*)
+	};
+	(@cdata [$($fmt:expr),*] [$($arg:expr),*] [$($rest:tt)*] { $e:expr } $($content:tt)*) => {
+		$crate::__format_xml_eraw!(@cdata [$($fmt,)* "{}"] [$($arg,)* $e] [$($rest)*] $($content)*)
+	};
+}
+
+/// Like [`format_xml!`], but rejects mismatched tags (e.g. `<a></b>`) and
+/// unclosed tags at compile time, instead of silently accepting them.
+///
+/// This threads a stack of currently-open tag names alongside the munch:
+/// opening `<name ...>` pushes `name`, `</name>` pops and compares it
+/// against the closing name, and self-closing/void/PI/comment/CDATA/doctype
+/// forms never push. At the end of the input the stack must be empty.
+/// Each `if`/`for`/`match` branch is checked independently, as its own
+/// balanced document.
+#[macro_export]
+macro_rules! format_xml_strict {
+	($($body:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [] [] [] $($body)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sbody {
+	// Done, and every opened tag was closed.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] []) => {
+		format_args!(concat!($($fmt),*) $(, $arg)*)
+	};
+
+	// Done, but one or more tags were never closed.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$([$($open:tt)*])+]) => {
+		compile_error!(concat!("format_xml_strict!: unclosed tag(s):", $(" <", concat!($($open)*), ">"),+))
+	};
+
+	// Closing tag. The name may be hyphen-/colon-separated (e.g. `tag-name`,
+	// `ns:tag`), which lexes as several idents punctuated by `-`/`:`, not a
+	// single `ident` token; `__format_xml_sclosename!` munches the rest of
+	// it, then pops the stack (or reports the "nothing to close" error).
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] </ $name:ident $($rest:tt)*) => {
+		$crate::__format_xml_sclosename!([$($fmt),*] [$($arg),*] [$($stack)*] [stringify!($name),] $($rest)*)
+	};
+
+	// Opening tag: accumulate the (possibly hyphen-/colon-separated) name,
+	// push it, then hand off to the stack-aware attribute muncher.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] < $name:ident $($rest:tt)*) => {
+		$crate::__format_xml_stagname!(@open [$($fmt,)* "<"] [$($arg),*] [$($stack)*] [stringify!($name),] $($rest)*)
+	};
+
+	// `<!doctype ...>`, does not push.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] < ! doctype $($rest:tt)*) => {
+		$crate::__format_xml_sraw!(@doctype [$($fmt,)* "<!doctype"] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+
+	// `<?target ...?>`, does not push.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] < ? $target:ident $($rest:tt)*) => {
+		$crate::__format_xml_stagname!(@pi [$($fmt,)* "<?"] [$($arg),*] [$($stack)*] [stringify!($target),] $($rest)*)
+	};
+
+	// `<!-- "comment" -->`, does not push.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] < ! - - $($rest:tt)*) => {
+		$crate::__format_xml_sraw!(@comment [$($fmt,)* "<!-- "] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+
+	// `<![CDATA["cdata"]]>`, does not push.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] < ! [ CDATA [ $($content:tt)* ] ] > $($rest:tt)*) => {
+		$crate::__format_xml_sraw!(@cdata [$($fmt,)* "<![CDATA["] [$($arg),*] [$($stack)*] [$($rest)*] $($content)*)
+	};
+
+	// `if let PAT = (expr) { body } else if (expr) { body } else { body }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] if let $pat:pat = ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sif!(@tail [$($fmt,)* "{}"] [$($arg),*] [$($stack)*] [if let $pat = ($cond) { $crate::format_xml_strict!($($then)*) }] $($rest)*)
+	};
+
+	// `if (expr) { body } else if (expr) { body } else { body }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sif!(@tail [$($fmt,)* "{}"] [$($arg),*] [$($stack)*] [if $cond { $crate::format_xml_strict!($($then)*) }] $($rest)*)
+	};
+
+	// `for PAT in (expr) { body }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] for $pat:pat in ( $iter:expr ) { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			for $pat in $iter {
+				::std::fmt::Write::write_fmt(f, $crate::format_xml_strict!($($body)*))?;
+			}
+			Ok(())
+		})] [$($stack)*] $($rest)*)
+	};
+
+	// `match (expr) { pat => { body } ... }`
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] match ( $e:expr ) { $($arms:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_smatch!(@arms [$($fmt,)* "{}"] [$($arg),*] [$($stack)*] [$e] [] $($arms)* @@ $($rest)*)
+	};
+
+	// A plain (unbound) statement, e.g. a `let` binding between nodes.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] let $pat:pat = $e:expr ; $($rest:tt)*) => {
+		{
+			let $pat = $e;
+			$crate::__format_xml_sbody!(@parse [$($fmt),*] [$($arg),*] [$($stack)*] $($rest)*)
+		}
+	};
+
+	// `{value;spec}` interpolation with a formatting specifier.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* concat!("{:", $(stringify!($spec)),* , "}")] [$($arg,)* $e] [$($stack)*] $($rest)*)
+	};
+
+	// `{value}` interpolation.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* "{}"] [$($arg,)* $e] [$($stack)*] $($rest)*)
+	};
+
+	// `|f| { ... }` escape hatch, see the crate documentation.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] |$f:ident| { $($block:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |$f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result { $($block)* Ok(()) })] [$($stack)*] $($rest)*)
+	};
+
+	// Text literal.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] $text:literal $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* "{}"] [$($arg,)* $text] [$($stack)*] $($rest)*)
+	};
+}
+
+/// Munches the hyphen-/colon-separated remainder of an opening tag or PI
+/// target name (e.g. the `-name` of `tag-name`, the `:tag` of `ns:tag`)
+/// into `[$($joined)*]`, a sequence of `concat!` pieces. `@open` pushes the
+/// joined name onto the stack (bracketed, so it stays one `tt` per entry)
+/// and hands off into [`__format_xml_sattrs!`]; `@pi` never pushes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_stagname {
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] - $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_stagname!(@$ctx [$($fmt),*] [$($arg),*] [$($stack)*] [$($joined)* "-", stringify!($next),] $($rest)*)
+	};
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] : $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_stagname!(@$ctx [$($fmt),*] [$($arg),*] [$($stack)*] [$($joined)* ":", stringify!($next),] $($rest)*)
+	};
+	(@open [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@parse [$($fmt,)* concat!($($joined)*)] [$($arg),*] [[$($joined)*] $($stack)*] $($rest)*)
+	};
+	(@pi [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@pi [$($fmt,)* concat!($($joined)*)] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+}
+
+/// Munches the hyphen-/colon-separated remainder of a closing tag's name
+/// into `[$($joined)*]`, a sequence of `concat!` pieces, then pops the
+/// stack and compares it against the joined name with
+/// [`__format_xml_tag_check!`] (or reports the "nothing to close" error if
+/// the stack is already empty).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sclosename {
+	([$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] - $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_sclosename!([$($fmt),*] [$($arg),*] [$($stack)*] [$($joined)* "-", stringify!($next),] $($rest)*)
+	};
+	([$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] : $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_sclosename!([$($fmt),*] [$($arg),*] [$($stack)*] [$($joined)* ":", stringify!($next),] $($rest)*)
+	};
+
+	// Nothing left open to close.
+	([$($fmt:expr),*] [$($arg:expr),*] [] [$($joined:tt)*] > $($rest:tt)*) => {
+		compile_error!(concat!("format_xml_strict!: closing tag </", concat!($($joined)*), "> has no matching open tag"))
+	};
+
+	// Pop the stack and compare it against the closing name.
+	([$($fmt:expr),*] [$($arg:expr),*] [[$($head:tt)*] $($tail:tt)*] [$($joined:tt)*] > $($rest:tt)*) => {
+		$crate::__format_xml_tag_check!([$($head)*], [$($joined)*], $crate::__format_xml_sbody!(@parse [$($fmt,)* concat!("</", $($joined)* ">")] [$($arg),*] [$($tail)*] $($rest)*))
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sif {
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sif!(@tail [$($fmt),*] [$($arg),*] [$($stack)*] [$($built)* else if $cond { $crate::format_xml_strict!($($then)*) }] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt),*] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { $crate::format_xml_strict!($($els)*) })
+		})] [$($stack)*] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($built:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt),*] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { format_args!("") })
+		})] [$($stack)*] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_smatch {
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$e:expr] [$($arms:tt)*] $pat:pat => { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_smatch!(@arms [$($fmt),*] [$($arg),*] [$($stack)*] [$e] [$($arms)* $pat => { $crate::format_xml_strict!($($body)*) }] $($rest)*)
+	};
+
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$e:expr] [$($arms:tt)*] @@ $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt),*] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, match $e { $($arms)* })
+		})] [$($stack)*] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sattrs {
+	// Self-closing tag: the name pushed on open never actually opened.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$head:tt $($tail:tt)*] / > $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* " />"] [$($arg),*] [$($tail)*] $($rest)*)
+	};
+	// Closing `>` of an opening tag: leave it on the stack.
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] > $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* ">"] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+	// Closing `?>` of a processing instruction (never pushed).
+	(@pi [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] ? > $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* "?>"] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+
+	// Attribute list is exhausted (only reachable from `@list`, used by the
+	// `for`/`if` attribute-position forms below).
+	(@list [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*]) => {
+		format_args!(concat!($($fmt),*) $(, $arg)*)
+	};
+
+	// `for PAT in (expr) { attrs }` in attribute-name position: emits zero
+	// or more complete `name="value"` pairs.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] for $pat:pat in ( $iter:expr ) { $($attrs:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			for $pat in $iter {
+				::std::fmt::Write::write_fmt(f, $crate::__format_xml_sattrs!(@list [] [] [] $($attrs)*))?;
+			}
+			Ok(())
+		})] [$($stack)*] $($rest)*)
+	};
+
+	// `if (expr) { attrs } else if (expr) { attrs } else { attrs }` in
+	// attribute-name position: emits zero or more complete `name="value"`
+	// pairs.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrs_if!(@tail $ctx [$($fmt),*] [$($arg),*] [$($stack)*] [if $cond { $crate::__format_xml_sattrs!(@list [] [] [] $($then)*) }] $($rest)*)
+	};
+
+	// `style=[..pairs]`: a dynamic list of `("prop", value)` pairs, each
+	// rendered as `prop: value;`.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] style = [ .. $iter:expr ] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* " style=\"{}\""] [$($arg,)* $crate::IterJoin(($iter).into_iter().map(|(p, v)| $crate::StyleProp(p, v)))] [$($stack)*] $($rest)*)
+	};
+
+	// Attribute name: start accumulating (possibly hyphen-/colon-separated,
+	// e.g. `stroke-width`, `xml:lang`) into `[$($joined)*]`, a sequence of
+	// `concat!` pieces, then dispatch on the value form once it's complete.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] $name:ident $($rest:tt)*) => {
+		$crate::__format_xml_sattr_name!(@$ctx [$($fmt),*] [$($arg),*] [$($stack)*] [stringify!($name),] $($rest)*)
+	};
+}
+
+/// Finishes munching a (possibly hyphen-/colon-separated) attribute name
+/// into `[$($joined)*]`, a sequence of `concat!` pieces, then dispatches on
+/// the value form that follows `=`, resuming [`__format_xml_sattrs!`] for
+/// the rest of the attribute list afterwards.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sattr_name {
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] - $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_sattr_name!(@$ctx [$($fmt),*] [$($arg),*] [$($stack)*] [$($joined)* "-", stringify!($next),] $($rest)*)
+	};
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] : $next:ident $($rest:tt)*) => {
+		$crate::__format_xml_sattr_name!(@$ctx [$($fmt),*] [$($arg),*] [$($stack)*] [$($joined)* ":", stringify!($next),] $($rest)*)
+	};
+
+	// `name=(if (expr) { value } else { value })` and
+	// `name=(match (expr) { pat => { value } ... })` control flow in
+	// attribute *value* position, each branch resolving to something
+	// `Display`-able and emitted inside the surrounding quotes.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = ( $($cf:tt)* ) $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $crate::__format_xml_sattrval!(@parse [] [] $($cf)*))
+		})] [$($stack)*] $($rest)*)
+	};
+
+	// `class=["a": cond_a, "b": cond_b]` and `style=[...]` specialised syntax.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = [ $($text:literal : $cond:expr),* $(,)? ] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::CondList(&[$(($text, $cond)),*])] [$($stack)*] $($rest)*)
+	};
+
+	// `name=[for PAT in (expr) { item }]`: a dynamic, space-separated list
+	// built from a runtime iterator.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = [ for $pat:pat in ( $iter:expr ) { $item:expr } ] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::IterJoin(($iter).into_iter().map(move |$pat| $item))] [$($stack)*] $($rest)*)
+	};
+
+	// `name=[..items]`: a dynamic, space-separated list built from a
+	// runtime iterator.
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = [ .. $iter:expr ] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $crate::IterJoin(($iter).into_iter())] [$($stack)*] $($rest)*)
+	};
+
+	// `name={value;spec}`
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{:", $(stringify!($spec)),* , "}\"")] [$($arg,)* $e] [$($stack)*] $($rest)*)
+	};
+
+	// `name={value}`
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $e] [$($stack)*] $($rest)*)
+	};
+
+	// `name="literal"`
+	(@$ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($joined:tt)*] = $value:literal $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* concat!(" ", $($joined)* "=\"{}\"")] [$($arg,)* $value] [$($stack)*] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sattrs_if {
+	(@tail $ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrs_if!(@tail $ctx [$($fmt),*] [$($arg),*] [$($stack)*] [$($built)* else if $cond { $crate::__format_xml_sattrs!(@list [] [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@tail $ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { $crate::__format_xml_sattrs!(@list [] [] [] $($els)*) })
+		})] [$($stack)*] $($rest)*)
+	};
+
+	(@tail $ctx:ident [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($built:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_sattrs!(@$ctx [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { format_args!("") })
+		})] [$($stack)*] $($rest)*)
+	};
+}
+
+/// Parses the control-flow forms allowed in attribute *value* position
+/// (inside the `( ... )` that follows `name=`). Tag balance only matters
+/// between element nodes, so these branches are parsed as plain value
+/// expressions, not as their own strict sub-documents.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sattrval {
+	(@parse [$($fmt:expr),*] [$($arg:expr),*]) => {
+		format_args!(concat!($($fmt),*) $(, $arg)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] if let $pat:pat = ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval_if!(@tail [$($fmt),*] [$($arg),*] [if let $pat = ($cond) { $crate::__format_xml_sattrval!(@parse [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval_if!(@tail [$($fmt),*] [$($arg),*] [if $cond { $crate::__format_xml_sattrval!(@parse [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] match ( $e:expr ) { $($arms:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval_match!(@arms [$($fmt),*] [$($arg),*] [$e] [] $($arms)* @@ $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] { $e:expr ; $($spec:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval!(@parse [$($fmt,)* concat!("{:", $(stringify!($spec)),* , "}")] [$($arg,)* $e] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $e] $($rest)*)
+	};
+
+	(@parse [$($fmt:expr),*] [$($arg:expr),*] $text:literal $($rest:tt)*) => {
+		$crate::__format_xml_sattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $text] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sattrval_if {
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else if ( $cond:expr ) { $($then:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval_if!(@tail [$($fmt),*] [$($arg),*] [$($built)* else if $cond { $crate::__format_xml_sattrval!(@parse [] [] $($then)*) }] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] else { $($els:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { $crate::__format_xml_sattrval!(@parse [] [] $($els)*) })
+		})] $($rest)*)
+	};
+
+	(@tail [$($fmt:expr),*] [$($arg:expr),*] [$($built:tt)*] $($rest:tt)*) => {
+		$crate::__format_xml_sattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, $($built)* else { format_args!("") })
+		})] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sattrval_match {
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$e:expr] [$($arms:tt)*] $pat:pat => { $($body:tt)* } $($rest:tt)*) => {
+		$crate::__format_xml_sattrval_match!(@arms [$($fmt),*] [$($arg),*] [$e] [$($arms)* $pat => { $crate::__format_xml_sattrval!(@parse [] [] $($body)*) }] $($rest)*)
+	};
+
+	(@arms [$($fmt:expr),*] [$($arg:expr),*] [$e:expr] [$($arms:tt)*] @@ $($rest:tt)*) => {
+		$crate::__format_xml_sattrval!(@parse [$($fmt,)* "{}"] [$($arg,)* $crate::FnFmt(move |f: &mut ::std::fmt::Formatter| -> ::std::fmt::Result {
+			::std::fmt::Write::write_fmt(f, match $e { $($arms)* })
+		})] $($rest)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_sraw {
+	(@doctype [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] > $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* ">"] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+	(@doctype [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] $t:tt $($rest:tt)*) => {
+		$crate::__format_xml_sraw!(@doctype [$($fmt,)* concat!(" ", stringify!($t))] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+
+	(@comment [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] - -> $($rest:tt)*) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* " -->"] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+	(@comment [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] $text:literal $($rest:tt)*) => {
+		$crate::__format_xml_sraw!(@comment [$($fmt,)* "{}"] [$($arg,)* $text] [$($stack)*] $($rest)*)
+	};
+	(@comment [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] { $e:expr } $($rest:tt)*) => {
+		$crate::__format_xml_sraw!(@comment [$($fmt,)* "{}"] [$($arg,)* $e] [$($stack)*] $($rest)*)
+	};
+
+	(@cdata [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($rest:tt)*]) => {
+		$crate::__format_xml_sbody!(@parse [$($fmt,)* "]]>"] [$($arg),*] [$($stack)*] $($rest)*)
+	};
+	(@cdata [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($rest:tt)*] $text:literal $($content:tt)*) => {
+		$crate::__format_xml_sraw!(@cdata [$($fmt,)* "{}"] [$($arg,)* $text] [$($stack)*] [$($rest)*] $($content)*)
+	};
+	(@cdata [$($fmt:expr),*] [$($arg:expr),*] [$($stack:tt)*] [$($rest:tt)*] { $e:expr } $($content:tt)*) => {
+		$crate::__format_xml_sraw!(@cdata [$($fmt,)* "{}"] [$($arg,)* $e] [$($stack)*] [$($rest)*] $($content)*)
+	};
+}
+
+/// Compares the popped stack head against a closing tag name, both given as
+/// joined-pieces token sequences (see [`__format_xml_stagname!`]) rather
+/// than a single `ident`, since either name may be hyphen-/colon-separated.
+///
+/// Unlike a single `ident`, a tt-sequence can't be compared by splicing it
+/// into a throwaway macro's literal-matching arm: that needs a nested
+/// `macro_rules!` whose fallback arm introduces a *new* repetition
+/// (`$($other:tt)*`), which a macro transcriber can't express once it
+/// already has repetitions of its own ([`$expected`]/[`$closing`]) in
+/// scope. Instead, join both sides into `&str`s with `concat!` and compare
+/// them byte-wise inside a `const` item, so a mismatch is still a hard
+/// compile error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_xml_tag_check {
+	([$($expected:tt)*], [$($closing:tt)*], $ok:expr) => {
+		{
+			const _: () = assert!(
+				$crate::__format_xml_str_eq(concat!($($expected)*), concat!($($closing)*)),
+				concat!("format_xml_strict!: closing tag </", concat!($($closing)*), "> does not match open tag <", concat!($($expected)*), ">"),
+			);
+			$ok
+		}
+	};
+}
+